@@ -0,0 +1,119 @@
+use std::io::{BufRead, BufReader, Write};
+use std::os::fd::{FromRawFd, IntoRawFd};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use nix::unistd::{geteuid, pipe};
+
+/// Populate `app_dir` with a real Debian rootfs via `debootstrap`. Falls
+/// back to PRoot (which fakes `chroot`/mknod for unprivileged users) when
+/// we're not running as root, since plain `debootstrap` needs to create
+/// device nodes and chroot.
+pub fn run_debootstrap(app_dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(app_dir)?;
+
+    let status = if geteuid().is_root() {
+        Command::new("debootstrap")
+            .arg("--variant=minbase")
+            .arg("bookworm")
+            .arg(app_dir)
+            .arg("http://deb.debian.org/debian")
+            .status()
+    } else {
+        Command::new("proot")
+            .arg("-0") // fake root inside proot
+            .arg("debootstrap")
+            .arg("--variant=minbase")
+            .arg("--no-check-gpg")
+            .arg("bookworm")
+            .arg(app_dir)
+            .arg("http://deb.debian.org/debian")
+            .status()
+    }
+    .context("failed to spawn debootstrap (is it installed?)")?;
+
+    if !status.success() {
+        return Err(anyhow::anyhow!("debootstrap exited with {}", status));
+    }
+    Ok(())
+}
+
+pub enum AptAction {
+    Install,
+    Remove,
+}
+
+impl AptAction {
+    fn as_apt_subcommand(&self) -> &'static str {
+        match self {
+            AptAction::Install => "install",
+            AptAction::Remove => "remove",
+        }
+    }
+}
+
+/// Run `apt-get <install|remove> <package>` inside the already-entered
+/// mount namespace (rootfs pivoted to `app_dir`), streaming dpkg's
+/// `Progress: [ NN%]` markers back over `progress_fd` so the caller's
+/// indicatif bar can track real progress instead of a simulated sleep loop.
+pub fn exec_apt_in_namespace(package: &str, action: AptAction, progress_fd: i32) -> Result<()> {
+    let mut child = Command::new("apt-get")
+        .arg("-y")
+        .arg("-o")
+        .arg("Dpkg::Progress-Fancy=0")
+        .arg(action.as_apt_subcommand())
+        .arg(package)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to spawn apt-get")?;
+
+    let stdout = child.stdout.take().expect("piped stdout");
+    let stderr = child.stderr.take().expect("piped stderr");
+
+    // Drain stderr on its own thread: apt-get routinely writes warnings,
+    // debconf prompts, and maintainer-script chatter there, and once that
+    // fills the pipe buffer, apt-get blocks on the write while we're stuck
+    // blocking on the stdout read below, deadlocking install/remove forever.
+    let stderr_thread = std::thread::spawn(move || {
+        BufReader::new(stderr).lines().map_while(Result::ok).collect::<Vec<_>>()
+    });
+
+    let mut progress_pipe = unsafe { std::fs::File::from_raw_fd(progress_fd) };
+
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+        if let Some(pct) = parse_progress_percent(&line) {
+            writeln!(progress_pipe, "{}", pct)?;
+        }
+    }
+
+    let status = child.wait()?;
+    let stderr_lines = stderr_thread.join().unwrap_or_default();
+    if !status.success() {
+        let detail = if stderr_lines.is_empty() {
+            String::new()
+        } else {
+            format!(": {}", stderr_lines.join("; "))
+        };
+        return Err(anyhow::anyhow!("apt-get {} {} exited with {}{}", action.as_apt_subcommand(), package, status, detail));
+    }
+    Ok(())
+}
+
+/// Parse apt's `Progress: [ 45%]` lines (emitted with
+/// `Dpkg::Progress-Fancy=0`) into a percentage.
+fn parse_progress_percent(line: &str) -> Option<u64> {
+    let rest = line.strip_prefix("Progress: [")?;
+    let rest = rest.trim_start();
+    let pct_str = rest.strip_suffix("%]")?;
+    pct_str.trim().parse().ok()
+}
+
+/// Create the pipe used to stream progress from the namespaced child back
+/// to the parent's progress bar.
+pub fn progress_pipe() -> Result<(i32, i32)> {
+    let (read_fd, write_fd) = pipe()?;
+    Ok((read_fd.into_raw_fd(), write_fd.into_raw_fd()))
+}
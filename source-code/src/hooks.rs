@@ -0,0 +1,155 @@
+use std::fs;
+use std::process::Command;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+
+/// A single post-install/post-remove lifecycle action, declared in the
+/// profile manifest so it re-runs deterministically on every `install`/
+/// `remove` instead of depending on whatever `apt`'s own maintainer scripts
+/// happened to trigger inside the namespace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Hook {
+    /// Rebuild the man-db index (`mandb`) so newly installed manpages show up.
+    ManDbIndex,
+    /// Recompile GLib/GSettings schemas so apps that ship a `.gschema.xml`
+    /// pick up their defaults.
+    GlibSchemas,
+    /// Append `shell` to `/etc/shells` so it's accepted as a login shell.
+    RegisterShell { shell: String },
+    /// Create a user entry in the rootfs's `/etc/passwd`.
+    CreateUser { name: String, uid: u32, gid: u32, shell: String },
+    /// Create a group entry in the rootfs's `/etc/group`.
+    CreateGroup { name: String, gid: u32 },
+}
+
+impl Hook {
+    fn label(&self) -> String {
+        match self {
+            Hook::ManDbIndex => "man-db index".to_string(),
+            Hook::GlibSchemas => "GSettings schemas".to_string(),
+            Hook::RegisterShell { shell } => format!("register shell {shell}"),
+            Hook::CreateUser { name, .. } => format!("create user {name}"),
+            Hook::CreateGroup { name, .. } => format!("create group {name}"),
+        }
+    }
+
+    /// Run the action. Assumes the caller has already `pivot_root`ed into
+    /// the profile's rootfs, so plain absolute paths (`/etc/passwd`, ...)
+    /// and bare command names resolve inside the namespace, not the host.
+    ///
+    /// `file_lock` serializes the hooks that do a read-modify-write on a
+    /// shared file (`/etc/passwd`, `/etc/group`, `/etc/shells`) so two
+    /// hooks appending to the same file from different threads can't race
+    /// and clobber each other's line.
+    fn run(&self, file_lock: &Mutex<()>) -> Result<()> {
+        match self {
+            Hook::ManDbIndex => run_ok(Command::new("mandb").status()),
+            Hook::GlibSchemas => run_ok(
+                Command::new("glib-compile-schemas")
+                    .arg("/usr/share/glib-2.0/schemas")
+                    .status(),
+            ),
+            Hook::RegisterShell { shell } => {
+                let _guard = file_lock.lock().unwrap();
+                append_if_missing("/etc/shells", shell)
+            }
+            Hook::CreateUser { name, uid, gid, shell } => {
+                let _guard = file_lock.lock().unwrap();
+                append_if_missing("/etc/passwd", &format!("{name}:x:{uid}:{gid}::/home/{name}:{shell}"))
+            }
+            Hook::CreateGroup { name, gid } => {
+                let _guard = file_lock.lock().unwrap();
+                append_if_missing("/etc/group", &format!("{name}:x:{gid}:"))
+            }
+        }
+    }
+}
+
+fn run_ok(status: std::io::Result<std::process::ExitStatus>) -> Result<()> {
+    let status = status.context("failed to spawn hook command")?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("hook command exited with {status}"));
+    }
+    Ok(())
+}
+
+/// Append `line` to `path` unless it's already present, so re-running the
+/// same hook (e.g. on a second `install`) stays idempotent.
+fn append_if_missing(path: &str, line: &str) -> Result<()> {
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    if existing.lines().any(|l| l == line) {
+        return Ok(());
+    }
+    let mut updated = existing;
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push_str(line);
+    updated.push('\n');
+    fs::write(path, updated).with_context(|| format!("writing {path}"))
+}
+
+/// Collects the hooks requested for a given install/remove and runs them.
+#[derive(Debug, Default)]
+pub struct Hooks {
+    requested: Vec<Hook>,
+}
+
+impl Hooks {
+    pub fn from_declared(hooks: &[Hook]) -> Self {
+        Hooks { requested: hooks.to_vec() }
+    }
+
+    /// Spawn each hook on its own thread, each with its own spinner in a
+    /// shared `MultiProgress` display, and collect their results back over an
+    /// `mpsc` channel. A hook that fails is recorded in its result but never
+    /// aborts its siblings.
+    pub fn run(self) -> Vec<(Hook, Result<()>)> {
+        if self.requested.is_empty() {
+            return Vec::new();
+        }
+
+        let multi = MultiProgress::new();
+        let (tx, rx) = mpsc::channel();
+        let file_lock = Arc::new(Mutex::new(()));
+
+        let handles: Vec<_> = self
+            .requested
+            .into_iter()
+            .map(|hook| {
+                let pb = multi.add(ProgressBar::new_spinner());
+                pb.set_style(ProgressStyle::default_spinner().template("{spinner:.green} {msg}").unwrap());
+                pb.enable_steady_tick(Duration::from_millis(100));
+                pb.set_message(hook.label());
+                let tx = tx.clone();
+                let file_lock = Arc::clone(&file_lock);
+
+                thread::spawn(move || {
+                    let result = hook.run(&file_lock);
+                    match &result {
+                        Ok(()) => pb.finish_with_message(format!("{} done", hook.label())),
+                        Err(err) => pb.finish_with_message(format!("{} failed: {err}", hook.label())),
+                    }
+                    let _ = tx.send((hook, result));
+                })
+            })
+            .collect();
+
+        drop(tx);
+
+        let mut results = Vec::new();
+        while let Ok(pair) = rx.recv() {
+            results.push(pair);
+        }
+        for handle in handles {
+            let _ = handle.join();
+        }
+        results
+    }
+}
@@ -0,0 +1,140 @@
+use std::fs;
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use nix::unistd::{Gid, Pid, Uid};
+
+use crate::oci::IdMapping;
+
+/// Map `pid`'s user namespace: identity-map the caller's own UID/GID to
+/// root (`0 <id> 1`), and, when `range_size` is non-zero, extend it with a
+/// contiguous subordinate range (`1 <subuid_base> <count>`) sourced from
+/// `/etc/subuid`/`/etc/subgid`. An unprivileged process can't write more
+/// than its own single identity line to `/proc/<pid>/{u,g}id_map`, so the
+/// extra range is delegated to the setuid `newuidmap`/`newgidmap` helpers,
+/// exactly as real container runtimes (podman, docker rootless) do.
+///
+/// Must be called from the parent of `pid`, after the child has unshared
+/// `CLONE_NEWUSER` but before it relies on its mapped identity for
+/// anything (mounts, capability drops, ...).
+pub fn configure(pid: Pid, uid: Uid, gid: Gid, range_size: u32) -> Result<()> {
+    if range_size == 0 {
+        write_identity_map(pid, "uid_map", uid.as_raw())?;
+        deny_setgroups(pid)?;
+        write_identity_map(pid, "gid_map", gid.as_raw())?;
+        return Ok(());
+    }
+
+    let username = username_for_uid(uid.as_raw())?;
+    let uid_range = lookup_range("/etc/subuid", uid.as_raw(), &username)?;
+    let gid_range = lookup_range("/etc/subgid", uid.as_raw(), &username)?;
+
+    run_newidmap("newuidmap", pid, uid.as_raw(), uid_range, range_size)?;
+    run_newidmap("newgidmap", pid, gid.as_raw(), gid_range, range_size)?;
+    Ok(())
+}
+
+/// Map `pid`'s user namespace from an OCI bundle spec's explicit
+/// `linux.uidMappings`/`gidMappings`, falling back to the identity-map
+/// `configure(pid, uid, gid, 0)` behavior when the spec declares none
+/// (the common case for a spec with no `user` namespace at all).
+pub fn configure_from_spec(pid: Pid, uid: Uid, gid: Gid, uid_mappings: &[IdMapping], gid_mappings: &[IdMapping]) -> Result<()> {
+    if uid_mappings.is_empty() && gid_mappings.is_empty() {
+        return configure(pid, uid, gid, 0);
+    }
+
+    run_newidmap_spec("newuidmap", pid, uid_mappings)?;
+    deny_setgroups(pid)?;
+    run_newidmap_spec("newgidmap", pid, gid_mappings)?;
+    Ok(())
+}
+
+/// Like `run_newidmap`, but writes an arbitrary list of spec-declared
+/// `containerID:hostID:size` triples instead of a single subordinate range.
+fn run_newidmap_spec(tool: &str, pid: Pid, mappings: &[IdMapping]) -> Result<()> {
+    if mappings.is_empty() {
+        return Ok(());
+    }
+
+    let mut cmd = Command::new(tool);
+    cmd.arg(pid.to_string());
+    for mapping in mappings {
+        cmd.arg(mapping.container_id.to_string());
+        cmd.arg(mapping.host_id.to_string());
+        cmd.arg(mapping.size.to_string());
+    }
+
+    let status = cmd.status().with_context(|| format!("failed to spawn {tool} (is it installed and setuid-root?)"))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("{tool} exited with {status}"));
+    }
+    Ok(())
+}
+
+/// A `<user>:<base>:<count>` entry resolved from `/etc/subuid`/`/etc/subgid`.
+#[derive(Debug, Clone, Copy)]
+struct SubIdRange {
+    base: u32,
+    count: u32,
+}
+
+fn write_identity_map(pid: Pid, file: &str, id: u32) -> Result<()> {
+    let path = format!("/proc/{pid}/{file}");
+    fs::write(&path, format!("0 {id} 1\n")).with_context(|| format!("writing {path}"))
+}
+
+fn deny_setgroups(pid: Pid) -> Result<()> {
+    let path = format!("/proc/{pid}/setgroups");
+    fs::write(&path, "deny\n").with_context(|| format!("writing {path}"))
+}
+
+fn run_newidmap(tool: &str, pid: Pid, id: u32, range: SubIdRange, requested: u32) -> Result<()> {
+    let count = range.count.min(requested);
+    let status = Command::new(tool)
+        .arg(pid.to_string())
+        .arg("0")
+        .arg(id.to_string())
+        .arg("1")
+        .arg("1")
+        .arg(range.base.to_string())
+        .arg(count.to_string())
+        .status()
+        .with_context(|| format!("failed to spawn {tool} (is it installed and setuid-root?)"))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("{tool} exited with {status}"));
+    }
+    Ok(())
+}
+
+/// Hand-parsed `/etc/passwd` lookup, matching the format [`crate::hooks`]
+/// already writes entries in.
+fn username_for_uid(uid: u32) -> Result<String> {
+    let passwd = fs::read_to_string("/etc/passwd").context("reading /etc/passwd")?;
+    for line in passwd.lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() >= 3 && fields[2] == uid.to_string() {
+            return Ok(fields[0].to_string());
+        }
+    }
+    Err(anyhow::anyhow!("no /etc/passwd entry for uid {uid}"))
+}
+
+/// Find the subordinate range `newuidmap`/`newgidmap` would grant this
+/// account: the first line keyed by either its name or its bare UID wins, as
+/// `/etc/subuid`/`/etc/subgid` allow either.
+fn lookup_range(path: &str, uid: u32, username: &str) -> Result<SubIdRange> {
+    let data = fs::read_to_string(path)
+        .with_context(|| format!("reading {path} (is a subordinate range allocated for this user?)"))?;
+    for line in data.lines() {
+        let fields: Vec<&str> = line.splitn(3, ':').collect();
+        if fields.len() != 3 {
+            continue;
+        }
+        if fields[0] == username || fields[0] == uid.to_string() {
+            let base: u32 = fields[1].parse().context("parsing subid base")?;
+            let count: u32 = fields[2].parse().context("parsing subid count")?;
+            return Ok(SubIdRange { base, count });
+        }
+    }
+    Err(anyhow::anyhow!("no entry for '{username}' (uid {uid}) in {path}"))
+}
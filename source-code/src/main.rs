@@ -1,6 +1,6 @@
 use std::env;
 use std::fs::{self, File};
-use std::io::Write;
+use std::os::fd::FromRawFd;
 use std::path::{Path, PathBuf};
 use anyhow::Result;
 use clap::{Parser, Subcommand, ArgGroup};
@@ -10,15 +10,21 @@ use inquire::Confirm;
 use nix::mount::{mount, MsFlags};
 use nix::sched::{unshare, CloneFlags};
 use nix::sys::stat::Mode;
-use nix::unistd::{chdir, execvp, fork, getuid, mkdir, pivot_root, ForkResult};
+use nix::unistd::{chdir, execvp, fork, getgid, getuid, mkdir, pivot_root, ForkResult};
 use nix::mount::umount2;
 use nix::mount::MntFlags;
 use nix::sys::wait::waitpid;
-use seccomp_sys::*;
 use tracing::{error, info};
 use tracing_subscriber::FmtSubscriber;
 use libc::{prctl, PR_CAPBSET_DROP, PR_SET_NO_NEW_PRIVS};
 
+mod bootstrap;
+mod hooks;
+mod idmap;
+mod oci;
+mod profile;
+mod seccomp_profile;
+
 #[derive(Parser)]
 #[clap(name = "isolator", about = "User-space isolation tool for HackerOS", version = "0.1.0")]
 struct Cli {
@@ -35,6 +41,20 @@ enum Commands {
         /// Shares to enable (comma-separated: home,wayland,x11,sound,tools)
         #[clap(long, value_delimiter = ',')]
         share: Vec<String>,
+        /// Path to a JSON seccomp profile (defaults to Isolator's restrictive built-in profile)
+        #[clap(long)]
+        seccomp: Option<PathBuf>,
+        /// Width of the subordinate UID/GID range to map in addition to the
+        /// identity-mapped owner, resolved from /etc/subuid and /etc/subgid
+        /// (0 keeps the legacy single-UID mapping)
+        #[clap(long, default_value_t = 0)]
+        id_range: u32,
+        /// Skip debootstrap and build a lightweight FHS root instead, by
+        /// bind-mounting the host's /usr, /bin, /lib, /lib64 and /etc
+        /// read-only and overlaying tmpfs on /home, /tmp and /var. Good for
+        /// quickly sandboxing dynamically-linked host binaries.
+        #[clap(long)]
+        fhs: bool,
     },
     /// Link one profile to another
     Link {
@@ -45,14 +65,25 @@ enum Commands {
     },
     /// Install a package into the environment
     Install {
+        /// Name of the application/profile to install into
+        app_name: String,
         /// Package name
         package: String,
     },
     /// Remove a package from the environment
     Remove {
+        /// Name of the application/profile to remove from
+        app_name: String,
         /// Package name
         package: String,
     },
+    /// List all known environments and their installed packages
+    List,
+    /// Run an OCI-runtime-style bundle (config.json + rootfs/)
+    Run {
+        /// Path to the bundle directory
+        bundle: PathBuf,
+    },
     /// Community install (not implemented)
     #[clap(group = ArgGroup::new("community"))]
     CommunityInstall {
@@ -81,10 +112,12 @@ fn main() -> Result<()> {
     fs::create_dir_all(&isolator_dir)?;
 
     match cli.command {
-        Commands::Create { app_name, share } => create_environment(&app_name, &share, &isolator_dir)?,
+        Commands::Create { app_name, share, seccomp, id_range, fhs } => create_environment(&app_name, &share, seccomp.as_deref(), id_range, fhs, &isolator_dir)?,
         Commands::Link { source, target } => link_profiles(&source, &target, &isolator_dir)?,
-        Commands::Install { package } => install_package(&package, &isolator_dir)?,
-        Commands::Remove { package } => remove_package(&package, &isolator_dir)?,
+        Commands::Run { bundle } => run_bundle(&bundle)?,
+        Commands::Install { app_name, package } => install_package(&app_name, &package, &isolator_dir)?,
+        Commands::Remove { app_name, package } => remove_package(&app_name, &package, &isolator_dir)?,
+        Commands::List => list_profiles(&isolator_dir)?,
         Commands::CommunityInstall { package: _ } => {
             println!("Community install not implemented yet.");
         }
@@ -96,7 +129,7 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn create_environment(app_name: &str, shares: &Vec<String>, isolator_dir: &Path) -> Result<()> {
+fn create_environment(app_name: &str, shares: &Vec<String>, seccomp: Option<&Path>, id_range: u32, fhs: bool, isolator_dir: &Path) -> Result<()> {
     info!("Creating environment for {}", app_name);
 
     // Create per-app rootfs dir
@@ -108,39 +141,63 @@ fn create_environment(app_name: &str, shares: &Vec<String>, isolator_dir: &Path)
     pb.set_style(ProgressStyle::default_spinner()
     .template("{spinner:.green} [{elapsed_precise}] {msg}")
     .unwrap());
-    pb.set_message("Setting up rootfs...");
+    pb.set_message(if fhs { "Setting up FHS skeleton..." } else { "Setting up rootfs..." });
 
     // Simulate debootstrap (in reality, exec debootstrap with fake root or use pre-built)
     // For demo, assume we copy a minimal rootfs or something
-    setup_rootfs(&app_dir)?;
+    setup_rootfs(&app_dir, fhs)?;
 
     pb.finish_with_message("Rootfs setup complete.");
 
+    // Record the profile manifest so install/remove/link/list can find this
+    // environment by name instead of relying on the caller repeating shares.
+    profile::save(isolator_dir, &profile::Profile::new(app_name, shares, id_range, fhs))?;
+
     // Ask for confirmation using inquire
     let ans = Confirm::new("Proceed to launch in isolated env?").prompt()?;
     if !ans {
         return Ok(());
     }
 
-    // Fork and unshare namespaces
+    // Fork and unshare namespaces. The child can't write its own multi-ID
+    // mapping (that needs the setuid newuidmap/newgidmap helpers run from
+    // outside the namespace), so it blocks on `sync_read` until the parent
+    // has finished mapping its pid. The parent in turn waits on `ready_read`
+    // until the child has unshared its user namespace.
+    let (sync_read, sync_write) = nix::unistd::pipe()?;
+    let (ready_read, ready_write) = nix::unistd::pipe()?;
     unsafe {
         match fork()? {
             ForkResult::Parent { child } => {
+                nix::unistd::close(sync_read)?;
+                nix::unistd::close(ready_write)?;
+                let mut ready_byte = [0u8; 1];
+                nix::unistd::read(ready_read, &mut ready_byte)?;
+                nix::unistd::close(ready_read)?;
+                idmap::configure(child, getuid(), getgid(), id_range)?;
+                nix::unistd::write(sync_write, &[0u8])?;
+                nix::unistd::close(sync_write)?;
                 // Wait for child
                 waitpid(child, None)?;
             }
             ForkResult::Child => {
+                nix::unistd::close(sync_write)?;
+                nix::unistd::close(ready_read)?;
                 // Unshare namespaces
                 unshare(CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWPID | CloneFlags::CLONE_NEWNET | CloneFlags::CLONE_NEWNS | CloneFlags::CLONE_NEWUTS | CloneFlags::CLONE_NEWIPC)?;
+                nix::unistd::write(ready_write, &[0u8])?;
+                nix::unistd::close(ready_write)?;
 
-                // Map user to root in new user ns
-                setup_user_namespace()?;
+                // Wait for the parent to finish writing our uid/gid maps
+                let mut sync_byte = [0u8; 1];
+                nix::unistd::read(sync_read, &mut sync_byte)?;
+                nix::unistd::close(sync_read)?;
 
                 // Setup mounts
-                setup_mounts(&app_dir, shares)?;
+                setup_mounts(&app_dir, shares, fhs)?;
 
                 // Drop capabilities
-                drop_capabilities()?;
+                drop_capabilities(&[])?;
 
                 // Set no_new_privs
                 if prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) < 0 {
@@ -148,7 +205,7 @@ fn create_environment(app_name: &str, shares: &Vec<String>, isolator_dir: &Path)
                 }
 
                 // Setup seccomp
-                setup_seccomp()?;
+                setup_seccomp(seccomp)?;
 
                 // Chdir and pivot_root
                 chdir(app_dir.as_os_str())?;
@@ -168,28 +225,208 @@ fn create_environment(app_name: &str, shares: &Vec<String>, isolator_dir: &Path)
     Ok(())
 }
 
-fn setup_rootfs(app_dir: &Path) -> Result<()> {
-    // In reality: exec debootstrap --variant=minbase bookworm <dir> http://deb.debian.org/debian
-    // But without root, need proot or fakechroot. For simplicity, simulate.
-    // Assume we have a minimal tarball or something.
-    fs::create_dir_all(app_dir.join("usr/bin"))?;
-    // Copy some binaries or simulate
+fn run_bundle(bundle: &Path) -> Result<()> {
+    info!("Running bundle {}", bundle.display());
+
+    let spec = oci::load_spec(bundle)?;
+    let rootfs = oci::rootfs_path(bundle);
+    let namespace_flags = oci::namespace_flags(&spec);
+
+    let (sync_read, sync_write) = nix::unistd::pipe()?;
+    let (ready_read, ready_write) = nix::unistd::pipe()?;
+    unsafe {
+        match fork()? {
+            ForkResult::Parent { child } => {
+                nix::unistd::close(sync_read)?;
+                // Wait until the child has unshared its namespaces.
+                nix::unistd::close(ready_write)?;
+                let mut ready_byte = [0u8; 1];
+                nix::unistd::read(ready_read, &mut ready_byte)?;
+                nix::unistd::close(ready_read)?;
+                // Only set up a uid/gid map when the spec actually unshares a
+                // user namespace: without CLONE_NEWUSER the child stays in our
+                // own (already-mapped) namespace, and writing to its
+                // uid_map/gid_map a second time is rejected by the kernel.
+                if namespace_flags.contains(CloneFlags::CLONE_NEWUSER) {
+                    idmap::configure_from_spec(child, getuid(), getgid(), &spec.linux.uid_mappings, &spec.linux.gid_mappings)?;
+                }
+                nix::unistd::write(sync_write, &[0u8])?;
+                nix::unistd::close(sync_write)?;
+                waitpid(child, None)?;
+            }
+            ForkResult::Child => {
+                nix::unistd::close(sync_write)?;
+                nix::unistd::close(ready_read)?;
+                unshare(namespace_flags)?;
+                nix::unistd::write(ready_write, &[0u8])?;
+                nix::unistd::close(ready_write)?;
+
+                let mut sync_byte = [0u8; 1];
+                nix::unistd::read(sync_read, &mut sync_byte)?;
+                nix::unistd::close(sync_read)?;
+
+                setup_bundle_mounts(&rootfs, &spec)?;
+                let kept_caps: Vec<String> =
+                    spec.process.capabilities.bounding.iter().chain(spec.process.capabilities.effective.iter()).cloned().collect();
+                drop_capabilities(&kept_caps)?;
+
+                if prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) < 0 {
+                    return Err(anyhow::anyhow!("Failed to set no_new_privs"));
+                }
+
+                setup_seccomp(None)?;
+
+                chdir(rootfs.as_os_str())?;
+                pivot_root(".", "old_root")?;
+                umount2("old_root", MntFlags::MNT_DETACH)?;
+
+                apply_readonly_and_masked_paths(&spec)?;
+
+                chdir(spec.process.cwd.as_str())?;
+
+                for (key, value) in oci::env_map(&spec.process) {
+                    env::set_var(key, value);
+                }
+
+                setup_seccomp(None)?;
+
+                let mut args = spec.process.args.iter();
+                let program = args.next().ok_or_else(|| anyhow::anyhow!("bundle process.args is empty"))?;
+                let cstr_program = std::ffi::CString::new(program.as_str())?;
+                let cstr_args: Vec<std::ffi::CString> = std::iter::once(cstr_program.clone())
+                    .chain(args.map(|a| std::ffi::CString::new(a.as_str()).unwrap()))
+                    .collect();
+                execvp(&cstr_program, &cstr_args)?;
+            }
+        }
+    }
+
     Ok(())
 }
 
-fn setup_user_namespace() -> Result<()> {
-    // Write uid_map and gid_map
-    let uid = getuid();
-    let mut uid_map = File::create("/proc/self/uid_map")?;
-    writeln!(uid_map, "0 {} 1", uid)?;
-    let mut gid_map = File::create("/proc/self/gid_map")?;
-    writeln!(gid_map, "0 {} 1", uid)?;
-    let mut setgroups = File::create("/proc/self/setgroups")?;
-    writeln!(setgroups, "deny")?;
+/// Pseudo-filesystem `mount.type` values that must be mounted as that
+/// filesystem (not bind-mounted), exactly like a real OCI bundle's default
+/// `config.json` (runc's included) declares for `/proc`, `/sys`, `/dev/pts`, ...
+const PSEUDO_FILESYSTEMS: &[&str] = &["proc", "sysfs", "tmpfs", "devpts", "mqueue", "cgroup"];
+
+/// Mount everything the bundle spec's `mounts` list describes onto the
+/// rootfs, in place of the ad-hoc share handling `setup_mounts` does for
+/// `create_environment`. Pseudo-filesystem entries (`proc`, `sysfs`, ...) are
+/// mounted as that filesystem type; everything else is bind-mounted from
+/// `source`, matching how runc/Docker bundles mix the two.
+fn setup_bundle_mounts(rootfs: &Path, spec: &oci::Spec) -> Result<()> {
+    for m in &spec.mounts {
+        let destination = rootfs.join(m.destination.trim_start_matches('/'));
+        fs::create_dir_all(&destination)?;
+
+        let is_pseudo_fs = m.mount_type.as_deref().is_some_and(|t| PSEUDO_FILESYSTEMS.contains(&t));
+
+        if is_pseudo_fs {
+            let fs_type = m.mount_type.as_deref().unwrap();
+            let (flags, data) = mount_option_flags(&m.options);
+            mount::<str, Path, str, str>(Some(fs_type), destination.as_path(), Some(fs_type), flags, data.as_deref())?;
+        } else {
+            let (mut flags, _data) = mount_option_flags(&m.options);
+            flags |= MsFlags::MS_BIND;
+            let source = m.source.as_deref().unwrap_or(m.destination.as_str());
+            mount::<str, Path, str, str>(Some(source), destination.as_path(), m.mount_type.as_deref(), flags, None)?;
+        }
+    }
+    Ok(())
+}
+
+/// Translate an OCI mount's `options` list (`"ro"`, `"nosuid"`, `"nodev"`,
+/// `"mode=1777"`, ...) into `mount(2)` flags plus a leftover `data` string
+/// for the options (like `mode=`/`size=`) the kernel expects as mount data
+/// rather than a flag.
+fn mount_option_flags(options: &[String]) -> (MsFlags, Option<String>) {
+    let mut flags = MsFlags::empty();
+    let mut data = Vec::new();
+    for option in options {
+        match option.as_str() {
+            "ro" => flags |= MsFlags::MS_RDONLY,
+            "nosuid" => flags |= MsFlags::MS_NOSUID,
+            "nodev" => flags |= MsFlags::MS_NODEV,
+            "noexec" => flags |= MsFlags::MS_NOEXEC,
+            "rw" => {}
+            other => data.push(other.to_string()),
+        }
+    }
+    let data = if data.is_empty() { None } else { Some(data.join(",")) };
+    (flags, data)
+}
+
+/// Apply the bundle spec's `readonlyPaths` and `maskedPaths`, replacing the
+/// previous hardcoded read-only `/usr` remount.
+fn apply_readonly_and_masked_paths(spec: &oci::Spec) -> Result<()> {
+    for path in &spec.linux.readonly_paths {
+        // `MS_REMOUNT` requires the target to already be a mountpoint, which
+        // an ordinary rootfs directory isn't, so bind it to itself first and
+        // only then remount that bind read-only (same two-step `setup_fhs_mounts` uses).
+        mount::<str, str, str, str>(Some(path.as_str()), path.as_str(), None, MsFlags::MS_BIND, None)?;
+        mount::<str, str, str, str>(Some(path.as_str()), path.as_str(), None, MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY, None)?;
+    }
+    for path in &spec.linux.masked_paths {
+        // A masked path can be a file (`/proc/kcore`) or a directory
+        // (`/proc/scsi`, `/sys/firmware`); bind-mounting the `/dev/null`
+        // character device onto a directory target fails with ENOTDIR, so
+        // mask directories with an empty read-only tmpfs instead.
+        if Path::new(path).is_dir() {
+            mount::<str, str, str, str>(Some("tmpfs"), path.as_str(), Some("tmpfs"), MsFlags::MS_RDONLY, None)?;
+        } else {
+            mount::<str, str, str, str>(Some("/dev/null"), path.as_str(), None, MsFlags::MS_BIND, None)?;
+        }
+    }
+    Ok(())
+}
+
+/// Host directories bind-mounted read-only into a `--fhs` environment.
+const FHS_BIND_DIRS: &[&str] = &["usr", "bin", "lib", "lib64", "etc"];
+/// Directories given a fresh, writable tmpfs in a `--fhs` environment.
+const FHS_OVERLAY_DIRS: &[&str] = &["home", "tmp", "var"];
+
+fn setup_rootfs(app_dir: &Path, fhs: bool) -> Result<()> {
+    if fhs {
+        setup_fhs_skeleton(app_dir)
+    } else {
+        bootstrap::run_debootstrap(app_dir)
+    }
+}
+
+/// Create the mount points a `--fhs` environment's bind/tmpfs mounts attach
+/// to. The mounts themselves happen later, in `setup_mounts`, once we're
+/// inside the profile's own mount namespace.
+fn setup_fhs_skeleton(app_dir: &Path) -> Result<()> {
+    for dir in FHS_BIND_DIRS.iter().chain(FHS_OVERLAY_DIRS.iter()) {
+        fs::create_dir_all(app_dir.join(dir))?;
+    }
+    Ok(())
+}
+
+/// Bind-mount the host's core FHS directories read-only into the per-app
+/// skeleton, then lay a tmpfs over the writable areas. This gives a
+/// dynamically-linked host binary a working root (it finds its loader and
+/// shared libraries) without the cost and storage of a `debootstrap` tree.
+fn setup_fhs_mounts(app_dir: &Path) -> Result<()> {
+    for dir in FHS_BIND_DIRS {
+        let source = Path::new("/").join(dir);
+        if !source.exists() {
+            continue;
+        }
+        let destination = app_dir.join(dir);
+        mount::<Path, Path, str, str>(Some(source.as_path()), destination.as_path(), None, MsFlags::MS_BIND, None)?;
+        mount::<Path, Path, str, str>(Some(source.as_path()), destination.as_path(), None, MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY, None)?;
+    }
+
+    for dir in FHS_OVERLAY_DIRS {
+        let destination = app_dir.join(dir);
+        mount::<str, Path, str, str>(Some("tmpfs"), destination.as_path(), Some("tmpfs"), MsFlags::empty(), None)?;
+    }
+
     Ok(())
 }
 
-fn setup_mounts(_app_dir: &Path, shares: &Vec<String>) -> Result<()> {
+fn setup_mounts(app_dir: &Path, shares: &Vec<String>, fhs: bool) -> Result<()> {
     // Mount proc, sys, dev, tmp
     mkdir("/proc", Mode::S_IRWXU)?;
     mount::<str, str, str, str>(Some("proc"), "/proc", Some("proc"), MsFlags::empty(), None)?;
@@ -197,6 +434,10 @@ fn setup_mounts(_app_dir: &Path, shares: &Vec<String>) -> Result<()> {
     // Similarly for sys, dev, tmpfs on /tmp
     // TODO: Add mounts for /sys, /dev, /tmp
 
+    if fhs {
+        setup_fhs_mounts(app_dir)?;
+    }
+
     // Bind shares
     for share in shares {
         match share.as_str() {
@@ -230,10 +471,18 @@ fn setup_mounts(_app_dir: &Path, shares: &Vec<String>) -> Result<()> {
     Ok(())
 }
 
-fn drop_capabilities() -> Result<()> {
-    // Drop all capabilities using libc
+/// Drop every bounding-set capability except those named in `keep` (OCI-style
+/// names, e.g. `"CAP_NET_BIND_SERVICE"`). An empty `keep` reproduces the
+/// previous unconditional drop-everything behavior used by `create_environment`.
+fn drop_capabilities(keep: &[String]) -> Result<()> {
+    let kept: Result<Vec<i32>> = keep.iter().map(|name| capability_by_name(name)).collect();
+    let kept = kept?;
+
     unsafe {
         for cap in 0..=40 {
+            if kept.contains(&cap) {
+                continue;
+            }
             if prctl(PR_CAPBSET_DROP, cap, 0, 0, 0) < 0 {
                 return Err(anyhow::anyhow!("Failed to drop capability {}", cap));
             }
@@ -242,22 +491,41 @@ fn drop_capabilities() -> Result<()> {
     Ok(())
 }
 
-fn setup_seccomp() -> Result<()> {
-    unsafe {
-        let ctx = seccomp_init(SCMP_ACT_ALLOW);
-        if ctx.is_null() {
-            return Err(anyhow::anyhow!("seccomp_init failed"));
-        }
-        // Deny ptrace
-        seccomp_rule_add(ctx, SCMP_ACT_ERRNO(libc::EPERM as u32), libc::SYS_ptrace as i32, 0);
-        // Deny mount
-        seccomp_rule_add(ctx, SCMP_ACT_ERRNO(libc::EPERM as u32), libc::SYS_mount as i32, 0);
-        // Deny kexec
-        seccomp_rule_add(ctx, SCMP_ACT_ERRNO(libc::EPERM as u32), libc::SYS_kexec_load as i32, 0);
-        seccomp_load(ctx);
-        seccomp_release(ctx);
-    }
-    Ok(())
+/// Resolve an OCI-style capability name (e.g. `"CAP_SYS_ADMIN"`) to its
+/// numeric value, the same constants `prctl(PR_CAPBSET_DROP, ...)` expects.
+fn capability_by_name(name: &str) -> Result<i32> {
+    Ok(match name {
+        "CAP_CHOWN" => libc::CAP_CHOWN,
+        "CAP_DAC_OVERRIDE" => libc::CAP_DAC_OVERRIDE,
+        "CAP_DAC_READ_SEARCH" => libc::CAP_DAC_READ_SEARCH,
+        "CAP_FOWNER" => libc::CAP_FOWNER,
+        "CAP_FSETID" => libc::CAP_FSETID,
+        "CAP_KILL" => libc::CAP_KILL,
+        "CAP_SETGID" => libc::CAP_SETGID,
+        "CAP_SETUID" => libc::CAP_SETUID,
+        "CAP_SETPCAP" => libc::CAP_SETPCAP,
+        "CAP_NET_BIND_SERVICE" => libc::CAP_NET_BIND_SERVICE,
+        "CAP_NET_ADMIN" => libc::CAP_NET_ADMIN,
+        "CAP_NET_RAW" => libc::CAP_NET_RAW,
+        "CAP_SYS_CHROOT" => libc::CAP_SYS_CHROOT,
+        "CAP_SYS_PTRACE" => libc::CAP_SYS_PTRACE,
+        "CAP_SYS_ADMIN" => libc::CAP_SYS_ADMIN,
+        "CAP_SYS_BOOT" => libc::CAP_SYS_BOOT,
+        "CAP_SYS_NICE" => libc::CAP_SYS_NICE,
+        "CAP_SYS_RESOURCE" => libc::CAP_SYS_RESOURCE,
+        "CAP_MKNOD" => libc::CAP_MKNOD,
+        "CAP_AUDIT_WRITE" => libc::CAP_AUDIT_WRITE,
+        "CAP_SETFCAP" => libc::CAP_SETFCAP,
+        other => return Err(anyhow::anyhow!("unknown capability name in spec: {}", other)),
+    })
+}
+
+fn setup_seccomp(profile_path: Option<&Path>) -> Result<()> {
+    let profile = match profile_path {
+        Some(path) => seccomp_profile::load_profile(path)?,
+        None => seccomp_profile::default_profile(),
+    };
+    seccomp_profile::apply(&profile)
 }
 
 fn link_profiles(source: &str, target: &str, isolator_dir: &Path) -> Result<()> {
@@ -268,44 +536,155 @@ fn link_profiles(source: &str, target: &str, isolator_dir: &Path) -> Result<()>
     fs::create_dir_all(&target_dir)?;
     // For example, symlink
     std::os::unix::fs::symlink(source_dir, target_dir.join("linked"))?;
+
+    // Record the relationship in the target's manifest (created if missing)
+    // and, when the source already has one, in the source's too, so `list`
+    // reflects it and the link survives beyond the symlink on disk.
+    let mut target_profile = profile::load(isolator_dir, target).unwrap_or_else(|_| profile::Profile::new(target, &[], 0, false));
+    if !target_profile.linked_profiles.iter().any(|p| p == source) {
+        target_profile.linked_profiles.push(source.to_string());
+    }
+    profile::save(isolator_dir, &target_profile)?;
+
+    if let Ok(mut source_profile) = profile::load(isolator_dir, source) {
+        if !source_profile.linked_profiles.iter().any(|p| p == target) {
+            source_profile.linked_profiles.push(target.to_string());
+        }
+        profile::save(isolator_dir, &source_profile)?;
+    }
+
     Ok(())
 }
 
-fn install_package(package: &str, _isolator_dir: &Path) -> Result<()> {
-    // Assume current dir is an app dir, but for global?
-    // Enter namespace and run apt install
-    // But complex; simulate with progress
-    let pb = ProgressBar::new(100);
-    pb.set_style(ProgressStyle::default_bar()
-    .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
-    .unwrap()
-    .progress_chars("#>-"));
-    pb.set_message(format!("Installing {}", package));
+fn install_package(app_name: &str, package: &str, isolator_dir: &Path) -> Result<()> {
+    let mut app_profile = profile::load(isolator_dir, app_name)?;
+    let app_dir = isolator_dir.join(app_name);
 
-    for i in 0..100 {
-        pb.set_position(i);
-        std::thread::sleep(std::time::Duration::from_millis(50));
+    run_namespaced_apt(&app_dir, package, bootstrap::AptAction::Install, "Installing", &app_profile.hooks, app_profile.id_range)?;
+
+    if !app_profile.packages.iter().any(|p| p == package) {
+        app_profile.packages.push(package.to_string());
     }
-    pb.finish_with_message("Installed.");
+    profile::save(isolator_dir, &app_profile)?;
 
     Ok(())
 }
 
-fn remove_package(package: &str, _isolator_dir: &Path) -> Result<()> {
-    // Similar to install
+/// Re-enter the profile's mount+PID namespace (the same `unshare`/
+/// `idmap::configure` sequence `create_environment` uses) and run
+/// `apt-get` against its rootfs, streaming real progress into the bar
+/// instead of the old fixed-duration simulated loop. Once `apt-get` is
+/// done, the profile's declared `hooks` run inside the same namespace so
+/// man-db/GSettings/user-and-group state stays in sync on every change.
+fn run_namespaced_apt(
+    app_dir: &Path,
+    package: &str,
+    action: bootstrap::AptAction,
+    verb: &str,
+    declared_hooks: &[hooks::Hook],
+    id_range: u32,
+) -> Result<()> {
     let pb = ProgressBar::new(100);
     pb.set_style(ProgressStyle::default_bar()
     .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
     .unwrap()
     .progress_chars("#>-"));
-    pb.set_message(format!("Removing {}", package));
+    pb.set_message(format!("{} {}", verb, package));
+
+    let done_message = match action {
+        bootstrap::AptAction::Install => "Installed.",
+        bootstrap::AptAction::Remove => "Removed.",
+    };
+
+    let (read_fd, write_fd) = bootstrap::progress_pipe()?;
+    let (sync_read, sync_write) = nix::unistd::pipe()?;
+    let (ready_read, ready_write) = nix::unistd::pipe()?;
+
+    unsafe {
+        match fork()? {
+            ForkResult::Parent { child } => {
+                nix::unistd::close(sync_read)?;
+                // Wait until the child has unshared its user namespace.
+                nix::unistd::close(ready_write)?;
+                let mut ready_byte = [0u8; 1];
+                nix::unistd::read(ready_read, &mut ready_byte)?;
+                nix::unistd::close(ready_read)?;
+                idmap::configure(child, getuid(), getgid(), id_range)?;
+                nix::unistd::write(sync_write, &[0u8])?;
+                nix::unistd::close(sync_write)?;
+
+                nix::unistd::close(write_fd)?;
+                let progress_file = File::from_raw_fd(read_fd);
+                for line in std::io::BufRead::lines(std::io::BufReader::new(progress_file)) {
+                    if let Ok(pct) = line?.trim().parse::<u64>() {
+                        pb.set_position(pct);
+                    }
+                }
+                waitpid(child, None)?;
+            }
+            ForkResult::Child => {
+                nix::unistd::close(sync_write)?;
+                nix::unistd::close(read_fd)?;
+                nix::unistd::close(ready_read)?;
+                unshare(CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWPID | CloneFlags::CLONE_NEWNS)?;
+                nix::unistd::write(ready_write, &[0u8])?;
+                nix::unistd::close(ready_write)?;
+
+                let mut sync_byte = [0u8; 1];
+                nix::unistd::read(sync_read, &mut sync_byte)?;
+                nix::unistd::close(sync_read)?;
+
+                chdir(app_dir.as_os_str())?;
+                pivot_root(".", "old_root")?;
+                umount2("old_root", MntFlags::MNT_DETACH)?;
+
+                mkdir("/proc", Mode::S_IRWXU)?;
+                mount::<str, str, str, str>(Some("proc"), "/proc", Some("proc"), MsFlags::empty(), None)?;
+
+                bootstrap::exec_apt_in_namespace(package, action, write_fd)?;
+
+                for (hook, result) in hooks::Hooks::from_declared(declared_hooks).run() {
+                    if let Err(err) = result {
+                        error!("hook {:?} failed: {err}", hook);
+                    }
+                }
+
+                std::process::exit(0);
+            }
+        }
+    }
+
+    pb.finish_with_message(done_message);
+    Ok(())
+}
+
+fn remove_package(app_name: &str, package: &str, isolator_dir: &Path) -> Result<()> {
+    let mut app_profile = profile::load(isolator_dir, app_name)?;
+    let app_dir = isolator_dir.join(app_name);
+
+    run_namespaced_apt(&app_dir, package, bootstrap::AptAction::Remove, "Removing", &app_profile.hooks, app_profile.id_range)?;
+
+    app_profile.packages.retain(|p| p != package);
+    profile::save(isolator_dir, &app_profile)?;
+
+    Ok(())
+}
 
-    for i in 0..100 {
-        pb.set_position(i);
-        std::thread::sleep(std::time::Duration::from_millis(50));
+fn list_profiles(isolator_dir: &Path) -> Result<()> {
+    let profiles = profile::load_all(isolator_dir)?;
+
+    let mut table = comfy_table::Table::new();
+    table.set_header(vec!["App", "Shares", "Packages", "Linked"]);
+    for p in &profiles {
+        table.add_row(vec![
+            p.app_name.clone(),
+            p.shares.join(", "),
+            p.packages.join(", "),
+            p.linked_profiles.join(", "),
+        ]);
     }
-    pb.finish_with_message("Removed.");
 
+    println!("{table}");
     Ok(())
 }
 
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// A parsed OCI-runtime-style `config.json`, covering the subset of the
+/// spec Isolator knows how to drive (process, mounts, and the linux
+/// namespace/id-mapping/path-masking knobs).
+#[derive(Debug, Deserialize)]
+pub struct Spec {
+    pub process: Process,
+    #[serde(default)]
+    pub mounts: Vec<Mount>,
+    pub linux: Linux,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Process {
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: Vec<String>,
+    pub cwd: String,
+    #[serde(default)]
+    pub capabilities: Capabilities,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Capabilities {
+    #[serde(default)]
+    pub bounding: Vec<String>,
+    #[serde(default)]
+    pub effective: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Mount {
+    pub destination: String,
+    pub source: Option<String>,
+    #[serde(rename = "type")]
+    pub mount_type: Option<String>,
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Linux {
+    #[serde(default)]
+    pub namespaces: Vec<Namespace>,
+    #[serde(rename = "uidMappings", default)]
+    pub uid_mappings: Vec<IdMapping>,
+    #[serde(rename = "gidMappings", default)]
+    pub gid_mappings: Vec<IdMapping>,
+    #[serde(rename = "maskedPaths", default)]
+    pub masked_paths: Vec<String>,
+    #[serde(rename = "readonlyPaths", default)]
+    pub readonly_paths: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Namespace {
+    #[serde(rename = "type")]
+    pub ns_type: String,
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdMapping {
+    #[serde(rename = "containerID")]
+    pub container_id: u32,
+    #[serde(rename = "hostID")]
+    pub host_id: u32,
+    pub size: u32,
+}
+
+/// Load and parse `<bundle>/config.json`. The bundle's `rootfs/` is
+/// resolved relative to `bundle` per the OCI runtime spec.
+pub fn load_spec(bundle: &Path) -> Result<Spec> {
+    let config_path = bundle.join("config.json");
+    let data = fs::read_to_string(&config_path)
+        .with_context(|| format!("reading {}", config_path.display()))?;
+    let spec: Spec = serde_json::from_str(&data)
+        .with_context(|| format!("parsing {}", config_path.display()))?;
+    Ok(spec)
+}
+
+pub fn rootfs_path(bundle: &Path) -> std::path::PathBuf {
+    bundle.join("rootfs")
+}
+
+/// Map the OCI `linux.namespaces` list to the `unshare` clone flags Isolator
+/// already knows how to set up.
+pub fn namespace_flags(spec: &Spec) -> nix::sched::CloneFlags {
+    use nix::sched::CloneFlags;
+
+    let mut flags = CloneFlags::empty();
+    for ns in &spec.linux.namespaces {
+        flags |= match ns.ns_type.as_str() {
+            "user" => CloneFlags::CLONE_NEWUSER,
+            "pid" => CloneFlags::CLONE_NEWPID,
+            "network" => CloneFlags::CLONE_NEWNET,
+            "mount" => CloneFlags::CLONE_NEWNS,
+            "uts" => CloneFlags::CLONE_NEWUTS,
+            "ipc" => CloneFlags::CLONE_NEWIPC,
+            "cgroup" => CloneFlags::CLONE_NEWCGROUP,
+            other => {
+                tracing::error!("Unknown namespace type in bundle spec: {}", other);
+                CloneFlags::empty()
+            }
+        };
+    }
+    flags
+}
+
+/// Build an env var map the way the spec's `process.env` (`KEY=VALUE`
+/// strings) describes it.
+pub fn env_map(process: &Process) -> HashMap<String, String> {
+    process
+        .env
+        .iter()
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::hooks::Hook;
+
+/// On-disk record of a sandboxed app, written to
+/// `~/.hackeros/isolator/<app>/profile.toml`. This is what makes `create`,
+/// `install`/`remove`, and `link` operate on a named, reproducible
+/// environment instead of a bare directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Profile {
+    pub app_name: String,
+    #[serde(default)]
+    pub shares: Vec<String>,
+    pub entrypoint: Option<String>,
+    #[serde(default)]
+    pub packages: Vec<String>,
+    #[serde(default)]
+    pub linked_profiles: Vec<String>,
+    /// Post-install/post-remove lifecycle actions, re-run deterministically
+    /// after every `install`/`remove` inside the profile's namespace.
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+    /// Width of the subordinate UID/GID range to map alongside the
+    /// identity-mapped owner (`0` keeps the legacy single-UID mapping).
+    #[serde(default)]
+    pub id_range: u32,
+    /// Whether this environment is a lightweight FHS bind-mount root
+    /// (host `/usr`, `/bin`, `/lib`, `/lib64`, `/etc`) instead of a
+    /// `debootstrap`-populated one.
+    #[serde(default)]
+    pub fhs: bool,
+}
+
+impl Profile {
+    pub fn new(app_name: &str, shares: &[String], id_range: u32, fhs: bool) -> Self {
+        Profile {
+            app_name: app_name.to_string(),
+            shares: shares.to_vec(),
+            entrypoint: Some(app_name.to_string()),
+            packages: Vec::new(),
+            linked_profiles: Vec::new(),
+            hooks: Vec::new(),
+            id_range,
+            fhs,
+        }
+    }
+}
+
+pub fn manifest_path(isolator_dir: &Path, app_name: &str) -> PathBuf {
+    isolator_dir.join(app_name).join("profile.toml")
+}
+
+pub fn save(isolator_dir: &Path, profile: &Profile) -> Result<()> {
+    let path = manifest_path(isolator_dir, &profile.app_name);
+    let toml = toml::to_string_pretty(profile)?;
+    fs::write(&path, toml).with_context(|| format!("writing {}", path.display()))?;
+    Ok(())
+}
+
+pub fn load(isolator_dir: &Path, app_name: &str) -> Result<Profile> {
+    let path = manifest_path(isolator_dir, app_name);
+    let data = fs::read_to_string(&path)
+        .with_context(|| format!("no profile for '{}' (expected {})", app_name, path.display()))?;
+    let profile: Profile = toml::from_str(&data).with_context(|| format!("parsing {}", path.display()))?;
+    Ok(profile)
+}
+
+/// Load every `profile.toml` directly under `isolator_dir`, skipping
+/// entries that aren't yet provisioned (e.g. a plain directory left behind
+/// by a failed `create`).
+pub fn load_all(isolator_dir: &Path) -> Result<Vec<Profile>> {
+    let mut profiles = Vec::new();
+    if !isolator_dir.exists() {
+        return Ok(profiles);
+    }
+    for entry in fs::read_dir(isolator_dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let app_name = entry.file_name().to_string_lossy().to_string();
+        if let Ok(profile) = load(isolator_dir, &app_name) {
+            profiles.push(profile);
+        }
+    }
+    Ok(profiles)
+}
@@ -0,0 +1,180 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use seccomp_sys::*;
+use serde::Deserialize;
+
+/// A seccomp profile, mirroring the shape of the profiles shipped by
+/// runtimes like runc/Docker: a default action, an optional architecture
+/// list, and a set of per-syscall rules.
+#[derive(Debug, Deserialize)]
+pub struct Profile {
+    #[serde(rename = "defaultAction")]
+    pub default_action: Action,
+    #[serde(default)]
+    pub architectures: Vec<String>,
+    pub syscalls: Vec<SyscallRule>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SyscallRule {
+    pub names: Vec<String>,
+    pub action: Action,
+    #[serde(rename = "errnoRet", default)]
+    pub errno_ret: Option<i32>,
+    #[serde(default)]
+    pub args: Vec<ArgRule>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArgRule {
+    pub index: u32,
+    pub value: u64,
+    pub op: CompareOp,
+}
+
+#[derive(Debug, Deserialize)]
+pub enum CompareOp {
+    #[serde(rename = "EQ")]
+    Eq,
+    #[serde(rename = "NE")]
+    Ne,
+    #[serde(rename = "GE")]
+    Ge,
+    #[serde(rename = "GT")]
+    Gt,
+    #[serde(rename = "LE")]
+    Le,
+    #[serde(rename = "LT")]
+    Lt,
+    #[serde(rename = "MASKED_EQ")]
+    MaskedEq,
+}
+
+impl CompareOp {
+    fn to_scmp(&self) -> scmp_compare {
+        match self {
+            CompareOp::Eq => scmp_compare::SCMP_CMP_EQ,
+            CompareOp::Ne => scmp_compare::SCMP_CMP_NE,
+            CompareOp::Ge => scmp_compare::SCMP_CMP_GE,
+            CompareOp::Gt => scmp_compare::SCMP_CMP_GT,
+            CompareOp::Le => scmp_compare::SCMP_CMP_LE,
+            CompareOp::Lt => scmp_compare::SCMP_CMP_LT,
+            CompareOp::MaskedEq => scmp_compare::SCMP_CMP_MASKED_EQ,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub enum Action {
+    #[serde(rename = "SCMP_ACT_ALLOW")]
+    Allow,
+    #[serde(rename = "SCMP_ACT_ERRNO")]
+    Errno,
+    #[serde(rename = "SCMP_ACT_KILL")]
+    Kill,
+}
+
+impl Action {
+    fn to_scmp(&self, errno_ret: Option<i32>) -> scmp_action {
+        match self {
+            Action::Allow => SCMP_ACT_ALLOW,
+            Action::Errno => SCMP_ACT_ERRNO(errno_ret.unwrap_or(libc::EPERM) as u32),
+            Action::Kill => SCMP_ACT_KILL,
+        }
+    }
+}
+
+/// Syscalls with no legitimate use inside a sandboxed app and a real
+/// potential for namespace/container escape or host tampering.
+const DEFAULT_DENIED_SYSCALLS: &[&str] =
+    &["ptrace", "mount", "umount2", "kexec_load", "reboot", "setns", "unshare", "init_module", "delete_module"];
+
+/// Isolator's restrictive default: allow everything by default (so
+/// ordinary app syscalls like `chdir`/`pivot_root`/`execve` keep working
+/// with no `--seccomp` flag at all) and explicitly deny the handful of
+/// syscalls a sandboxed app has no legitimate reason to call.
+pub fn default_profile() -> Profile {
+    Profile {
+        default_action: Action::Allow,
+        architectures: Vec::new(),
+        syscalls: DEFAULT_DENIED_SYSCALLS
+            .iter()
+            .map(|name| SyscallRule {
+                names: vec![(*name).to_string()],
+                action: Action::Errno,
+                errno_ret: Some(libc::EPERM),
+                args: Vec::new(),
+            })
+            .collect(),
+    }
+}
+
+pub fn load_profile(path: &Path) -> Result<Profile> {
+    let data = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let profile: Profile = serde_json::from_str(&data).with_context(|| format!("parsing {}", path.display()))?;
+    Ok(profile)
+}
+
+/// Initialize a seccomp context from `profile` and load it into the
+/// kernel, replacing the three inline `seccomp_rule_add` calls that used
+/// to hardcode `setup_seccomp`'s behavior.
+pub fn apply(profile: &Profile) -> Result<()> {
+    unsafe {
+        let ctx = seccomp_init(profile.default_action.to_scmp(None));
+        if ctx.is_null() {
+            return Err(anyhow::anyhow!("seccomp_init failed"));
+        }
+
+        for arch in &profile.architectures {
+            let token = resolve_arch(arch)?;
+            seccomp_arch_add(ctx, token);
+        }
+
+        for rule in &profile.syscalls {
+            for name in &rule.names {
+                let syscall_nr = resolve_syscall(name)?;
+                let action = rule.action.to_scmp(rule.errno_ret);
+                if rule.args.is_empty() {
+                    seccomp_rule_add(ctx, action, syscall_nr, 0);
+                } else {
+                    // All of a rule's `args` must hold simultaneously (e.g.
+                    // "this socket() call with this domain AND this type"),
+                    // so they go into one rule as conjoined conditions, not
+                    // one independent (OR'd) rule per condition.
+                    let cmps: Vec<scmp_arg_cmp> = rule
+                        .args
+                        .iter()
+                        .map(|arg| scmp_arg_cmp { arg: arg.index, op: arg.op.to_scmp(), datum_a: arg.value, datum_b: 0 })
+                        .collect();
+                    seccomp_rule_add_array(ctx, action, syscall_nr, cmps.len() as u32, cmps.as_ptr());
+                }
+            }
+        }
+
+        seccomp_load(ctx);
+        seccomp_release(ctx);
+    }
+    Ok(())
+}
+
+/// Resolve a syscall name (e.g. `"ptrace"`) to its number via libseccomp's
+/// own resolver, so any syscall libseccomp knows about works in a custom
+/// profile instead of only the handful Isolator happened to hardcode.
+fn resolve_syscall(name: &str) -> Result<i32> {
+    let cname = std::ffi::CString::new(name).with_context(|| format!("invalid syscall name: {}", name))?;
+    let nr = unsafe { seccomp_syscall_resolve_name(cname.as_ptr()) };
+    if nr < 0 {
+        return Err(anyhow::anyhow!("unknown syscall name in seccomp profile: {}", name));
+    }
+    Ok(nr)
+}
+
+fn resolve_arch(arch: &str) -> Result<u32> {
+    match arch {
+        "SCMP_ARCH_X86_64" => Ok(SCMP_ARCH_X86_64),
+        "SCMP_ARCH_AARCH64" => Ok(SCMP_ARCH_AARCH64),
+        other => Err(anyhow::anyhow!("unknown architecture in seccomp profile: {}", other)),
+    }
+}